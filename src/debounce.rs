@@ -0,0 +1,45 @@
+use debouncr::Edge;
+
+/// Integrator debouncer, counts toward a cap while pressed and back to 0 while
+/// released, and only flips its state at either end
+#[derive(Debug, Copy, Clone)]
+pub struct Integrator {
+    count: u8,
+    state: bool,
+}
+
+impl Integrator {
+    /// Create a new Integrator, released.
+    pub const fn new() -> Self {
+        Integrator {
+            count: 0,
+            state: false,
+        }
+    }
+
+    /// Step toward the pin state and return an edge when the state flips.
+    pub fn update(&mut self, is_pressed: bool, cap: u8) -> Option<Edge> {
+        // A cap of 0 would flip on every read
+        let cap = cap.max(1);
+        if is_pressed {
+            self.count = self.count.saturating_add(1).min(cap);
+        } else {
+            self.count = self.count.saturating_sub(1);
+        }
+
+        if !self.state && self.count >= cap {
+            self.state = true;
+            Some(Edge::Rising)
+        } else if self.state && self.count == 0 {
+            self.state = false;
+            Some(Edge::Falling)
+        } else {
+            None
+        }
+    }
+
+    /// If the integrated state is pressed
+    pub fn is_high(&self) -> bool {
+        self.state
+    }
+}
@@ -4,34 +4,67 @@
 use panic_halt as _;
 use arduino_hal;
 mod report;
-use report::KeyData;
+use report::*;
 pub mod switches;
 use switches::Switch;
+mod socd;
+mod debounce;
+use socd::{resolve_socd, SocdTieBreak};
 
-// Button state masks
-static MASK_A: u16 = 0x0004;
-static MASK_B: u16 = 0x0002;
-static MASK_X: u16 = 0x0008;
-static MASK_Y: u16 = 0x0001;
-static MASK_L1: u16 = 0x0010;
-static MASK_R1: u16 = 0x0020;
-static MASK_L2: u16 = 0x0040;
-static MASK_R2: u16 = 0x0080;
-static MASK_SELECT: u16 = 0x0100;
-static MASK_START: u16 = 0x0200;
-static MASK_HOME: u16 = 0x1000;
-static MASK_NONE: u16 = 0x0000;
-
-// Dpad Hat switch state masks
-static PAD_MASK_UP: u8 = 0x00;
-static PAD_MASK_UPRIGHT: u8 = 0x01;
-static PAD_MASK_RIGHT: u8 = 0x02;
-static PAD_MASK_DOWNRIGHT: u8 = 0x03;
-static PAD_MASK_DOWN: u8 = 0x04;
-static PAD_MASK_DOWNLEFT: u8 = 0x05;
-static PAD_MASK_LEFT: u8 = 0x06;
-static PAD_MASK_UPLEFT: u8 = 0x07;
-static PAD_MASK_NONE: u8 = 0x08;
+// SOCD tie-breaks for opposing directions pressed on the same frame
+// Applies to every mode, except the Dpad mode's own Shift handling
+// A common hitbox setup is Left+Right neutral and Up+Down UpOrLeft
+static SOCD_LEFT_RIGHT: SocdTieBreak = SocdTieBreak::Neutral;
+static SOCD_UP_DOWN: SocdTieBreak = SocdTieBreak::Neutral;
+// Buttons pressed when the tie-break cleans Left+Right to neutral, in every mode
+// 0x0000 disables
+static SOCD_DASH_MASK: Buttons = 0x0000;
+
+// Report the Dpad mode's directions as the hat, as four buttons, or both
+#[derive(Debug, Copy, Clone)]
+enum DpadOutput {
+    Hat,
+    Buttons,
+    HatAndButtons,
+}
+static DPAD_AS_BUTTONS: DpadOutput = DpadOutput::Hat;
+
+// Diagonal stick magnitude, out of 255
+// 255 keeps square diagonals, 181 (~0.7x) gives a circular feel
+static DIAGONAL_SCALE: u8 = 255;
+
+// Signed bias added to each stick axis after processing: LX, LY, RX, RY
+static AXIS_TRIM: [i8; 4] = [0, 0, 0, 0];
+
+// Physical rotation of the dpad, clockwise, for rotated installs
+#[derive(Debug, Copy, Clone)]
+enum DpadRotation {
+    None,
+    Cw90,
+    Cw180,
+    Cw270,
+}
+static DPAD_ROTATION: DpadRotation = DpadRotation::None;
+static DPAD_FLIP_X: bool = false;
+static DPAD_FLIP_Y: bool = false;
+
+// Debounce every switch, directions and Shift included
+// false passes raw pin reads straight through for the lowest latency
+static DEBOUNCE_ENABLED: bool = true;
+// Pattern is debouncr's default, Integrator { cap } rides out intermittent noise better
+static DEBOUNCE_ALGORITHM: switches::DebounceAlgorithm = switches::DebounceAlgorithm::Pattern;
+// Integrator cap for each group of switches, None keeps DEBOUNCE_ALGORITHM
+static DEBOUNCE_GROUPS: [(switches::ButtonGroup, Option<u8>); 4] = [
+    (switches::ButtonGroup::Dpad, None),
+    (switches::ButtonGroup::Face, None),
+    (switches::ButtonGroup::Shoulder, None),
+    (switches::ButtonGroup::Menu, None),
+];
+
+// Polls Home must be held before it is reported, so a brushed Home can't
+// suspend the console. 0 reports Home at once. Shift+Home reads the Home
+// switch, not the report bit, so mode changes still work with the guard on.
+static HOME_HOLD_FRAMES: u32 = 0;
 
 // Mode Selection
 #[derive(Debug, Copy, Clone)]
@@ -48,7 +81,7 @@ fn process_mode_change (
     _changed: &mut bool, 
     indicators: &mut [arduino_hal::port::Pin<arduino_hal::port::mode::Output>; 2]
 ) -> InputMode {
-    if !*_changed && buttons[switches::SWITCH_SHIFT].is_pressed() && buttons[switches::SWITCH_HOME].is_pressed() {
+    if !*_changed && buttons[switches::SWITCH_SHIFT].is_high() && buttons[switches::SWITCH_HOME].is_high() {
         match mode {
             InputMode::Dpad => {
                 mode = InputMode::Analog;
@@ -74,66 +107,149 @@ fn process_mode_change (
     }
 }
 
+// Switch offsets read as logical up, down, left and right
+// Rotation is applied first, then the optional axis flips
+fn dpad_switches() -> (usize, usize, usize, usize) {
+    let (up, down, left, right) = (
+        switches::SWITCH_UP,
+        switches::SWITCH_DOWN,
+        switches::SWITCH_LEFT,
+        switches::SWITCH_RIGHT,
+    );
+    let (mut up, mut down, mut left, mut right) = match DPAD_ROTATION {
+        DpadRotation::None => (up, down, left, right),
+        DpadRotation::Cw90 => (left, right, down, up),
+        DpadRotation::Cw180 => (down, up, right, left),
+        DpadRotation::Cw270 => (right, left, up, down),
+    };
+    if DPAD_FLIP_X {
+        core::mem::swap(&mut left, &mut right);
+    }
+    if DPAD_FLIP_Y {
+        core::mem::swap(&mut up, &mut down);
+    }
+    (up, down, left, right)
+}
+
+// Read the directions through the dpad rotation and clean opposing pairs
+// Returns the resolved up, down, left and right
+fn read_directions(buttons: &[Switch]) -> (bool, bool, bool, bool) {
+    let (switch_up, switch_down, switch_left, switch_right) = dpad_switches();
+    let (up, down) = resolve_socd(
+        buttons[switch_up].is_high(),
+        buttons[switch_down].is_high(),
+        SOCD_UP_DOWN,
+    );
+    let (left, right) = resolve_socd(
+        buttons[switch_left].is_high(),
+        buttons[switch_right].is_high(),
+        SOCD_LEFT_RIGHT,
+    );
+    (up, down, left, right)
+}
+
+// Scale a stick's deflection from its neutral when both axes are pushed
+fn scale_diagonal(x: &mut u8, y: &mut u8, neutral_x: u8, neutral_y: u8) {
+    if *x != neutral_x && *y != neutral_y {
+        *x = scale_axis(*x, neutral_x);
+        *y = scale_axis(*y, neutral_y);
+    }
+}
+
+fn scale_axis(value: u8, neutral: u8) -> u8 {
+    let offset = value as i32 - neutral as i32;
+    (neutral as i32 + offset * DIAGONAL_SCALE as i32 / 255) as u8
+}
+
+// Apply a trim to an axis, clamped to the report range
+fn trim_axis(value: u8, trim: i8) -> u8 {
+    (value as i16 + trim as i16).clamp(0, 255) as u8
+}
+
 fn process_smash(buttons: &[Switch], stickreport: &mut report::KeyData) -> report::KeyData {
     // Analog modes don't change the dpad state
-    // Treat the directions as analog input
+    // Treat the directions as analog input, HID Y grows downward so up is 0
     // shift makes half values
-    if buttons[switches::SWITCH_SHIFT].is_pressed() {
-        if buttons[switches::SWITCH_UP].is_pressed() {
-            stickreport.ly = 192;
-        } else if buttons[switches::SWITCH_DOWN].is_pressed() {
+    let (up, down, left, right) = read_directions(buttons);
+    if buttons[switches::SWITCH_SHIFT].is_high() {
+        if up {
             stickreport.ly = 64;
+        } else if down {
+            stickreport.ly = 192;
         }
-        if buttons[switches::SWITCH_LEFT].is_pressed() {
+        if left {
             stickreport.lx = 64;
-        } else if buttons[switches::SWITCH_RIGHT].is_pressed() {
+        } else if right {
             stickreport.lx = 192;
         }
     } else { // report max values for axies
-        if buttons[switches::SWITCH_UP].is_pressed() {
-            stickreport.ly = 255;
-        } else if buttons[switches::SWITCH_DOWN].is_pressed() {
+        if up {
             stickreport.ly = 0;
+        } else if down {
+            stickreport.ly = 255;
         }
-        if buttons[switches::SWITCH_LEFT].is_pressed() {
+        if left {
             stickreport.lx = 0;
-        } else if buttons[switches::SWITCH_RIGHT].is_pressed() {
+        } else if right {
             stickreport.lx = 255;
         }
     }
+    scale_diagonal(&mut stickreport.lx, &mut stickreport.ly, AXIS_NEUTRAL[0], AXIS_NEUTRAL[1]);
     return *stickreport;
 }
 
 fn process_analog(buttons: &[Switch], stickreport: &mut KeyData) -> KeyData {
     // Analog modes don't change the dpad state
-    // Treat the directions as analog input
+    // Treat the directions as analog input, HID Y grows downward so up is 0
     // shift makes the input register right stick
-    if buttons[switches::SWITCH_SHIFT].is_pressed() {
-        if buttons[switches::SWITCH_UP].is_pressed() {
-            stickreport.ry = 255;
-        } else if buttons[switches::SWITCH_DOWN].is_pressed() {
+    // with single-stick there is no right stick, so shift is ignored here
+    let (up, down, left, right) = read_directions(buttons);
+    if !cfg!(feature = "single-stick") && buttons[switches::SWITCH_SHIFT].is_high() {
+        if up {
             stickreport.ry = 0;
+        } else if down {
+            stickreport.ry = 255;
         }
-        if buttons[switches::SWITCH_LEFT].is_pressed() {
+        if left {
             stickreport.rx = 0;
-        } else if buttons[switches::SWITCH_RIGHT].is_pressed() {
+        } else if right {
             stickreport.rx = 255;
         }
     } else {
-        if buttons[switches::SWITCH_UP].is_pressed() {
-            stickreport.ly = 255;
-        } else if buttons[switches::SWITCH_DOWN].is_pressed() {
+        if up {
             stickreport.ly = 0;
+        } else if down {
+            stickreport.ly = 255;
         }
-        if buttons[switches::SWITCH_LEFT].is_pressed() {
+        if left {
             stickreport.lx = 0;
-        } else if buttons[switches::SWITCH_RIGHT].is_pressed() {
+        } else if right {
             stickreport.lx = 255;
         }
     }
+    scale_diagonal(&mut stickreport.lx, &mut stickreport.ly, AXIS_NEUTRAL[0], AXIS_NEUTRAL[1]);
+    scale_diagonal(&mut stickreport.rx, &mut stickreport.ry, AXIS_NEUTRAL[2], AXIS_NEUTRAL[3]);
     return *stickreport;
 }
 
+// Dpad button bits for a resolved hat value
+fn hat_buttons(hat: u8) -> Buttons {
+    let mut buttons = MASK_NONE;
+    if hat == PAD_MASK_UPLEFT || hat == PAD_MASK_UP || hat == PAD_MASK_UPRIGHT {
+        buttons |= MASK_DPAD_UP;
+    }
+    if hat == PAD_MASK_UPRIGHT || hat == PAD_MASK_RIGHT || hat == PAD_MASK_DOWNRIGHT {
+        buttons |= MASK_DPAD_RIGHT;
+    }
+    if hat == PAD_MASK_DOWNRIGHT || hat == PAD_MASK_DOWN || hat == PAD_MASK_DOWNLEFT {
+        buttons |= MASK_DPAD_DOWN;
+    }
+    if hat == PAD_MASK_DOWNLEFT || hat == PAD_MASK_LEFT || hat == PAD_MASK_UPLEFT {
+        buttons |= MASK_DPAD_LEFT;
+    }
+    return buttons;
+}
+
 fn process_dpad(buttons: &[Switch], stickreport: &mut KeyData) -> KeyData {
     // Dpad modes don't change the analog state
     // Treat the directions as digital input
@@ -145,19 +261,20 @@ fn process_dpad(buttons: &[Switch], stickreport: &mut KeyData) -> KeyData {
     // Shift first negates left and right when up or down is pressed
     // Next, it negates up if left and right were not present
     // Then it changes Down to UP if present.
-    if buttons[switches::SWITCH_SHIFT].is_pressed() {
-        if buttons[switches::SWITCH_UP].is_pressed() {
-            if buttons[switches::SWITCH_LEFT].is_pressed() {
+    let (switch_up, switch_down, switch_left, switch_right) = dpad_switches();
+    if buttons[switches::SWITCH_SHIFT].is_high() {
+        if buttons[switch_up].is_high() {
+            if buttons[switch_left].is_high() {
                 stickreport.hat = PAD_MASK_UP;
-            } else if buttons[switches::SWITCH_RIGHT].is_pressed() {
+            } else if buttons[switch_right].is_high() {
                 stickreport.hat = PAD_MASK_UP;
             } else {
                 stickreport.hat = PAD_MASK_NONE;
             }
-        } else if buttons[switches::SWITCH_DOWN].is_pressed() {
-            if buttons[switches::SWITCH_LEFT].is_pressed() {
+        } else if buttons[switch_down].is_high() {
+            if buttons[switch_left].is_high() {
                 stickreport.hat = PAD_MASK_DOWN;
-            } else if buttons[switches::SWITCH_RIGHT].is_pressed() {
+            } else if buttons[switch_right].is_high() {
                 stickreport.hat = PAD_MASK_DOWN;
             } else {
                 stickreport.hat = PAD_MASK_UP;
@@ -166,86 +283,122 @@ fn process_dpad(buttons: &[Switch], stickreport: &mut KeyData) -> KeyData {
             stickreport.hat = PAD_MASK_NONE;
         }
     // Without Shift pressed, the directions are normal
+    // Opposing directions are cleaned with the SOCD tie-break first
     } else {
-        if buttons[switches::SWITCH_UP].is_pressed() {
-            if buttons[switches::SWITCH_LEFT].is_pressed() {
+        let (up, down, left, right) = read_directions(buttons);
+        if up {
+            if left {
                 stickreport.hat = PAD_MASK_UPLEFT;
-            } else if buttons[switches::SWITCH_RIGHT].is_pressed() {
+            } else if right {
                 stickreport.hat = PAD_MASK_UPRIGHT;
             } else {
                 stickreport.hat = PAD_MASK_UP;
             }
-        } else if buttons[switches::SWITCH_DOWN].is_pressed() {
-            if buttons[switches::SWITCH_LEFT].is_pressed() {
+        } else if down {
+            if left {
                 stickreport.hat = PAD_MASK_DOWNLEFT;
-            } else if buttons[switches::SWITCH_RIGHT].is_pressed() {
+            } else if right {
                 stickreport.hat = PAD_MASK_DOWNRIGHT;
             } else {
                 stickreport.hat = PAD_MASK_DOWN;
             }
-        } else if buttons[switches::SWITCH_LEFT].is_pressed() {
+        } else if left {
             stickreport.hat = PAD_MASK_LEFT;
-        } else if buttons[switches::SWITCH_RIGHT].is_pressed() {
+        } else if right {
             stickreport.hat = PAD_MASK_RIGHT;
         } else {
             stickreport.hat = PAD_MASK_NONE;
         }
     }
+
+    // The directions are SOCD-cleaned by now, mirror them onto the buttons
+    match DPAD_AS_BUTTONS {
+        DpadOutput::Hat => {},
+        DpadOutput::Buttons => {
+            stickreport.buttons |= hat_buttons(stickreport.hat);
+            stickreport.hat = PAD_MASK_NONE;
+        },
+        DpadOutput::HatAndButtons => {
+            stickreport.buttons |= hat_buttons(stickreport.hat);
+        },
+    }
     return *stickreport;
 }
 
+// Base button layer as (switch offset, report mask) pairs
+static BASE_LAYER: &[(usize, Buttons)] = &[
+    (switches::SWITCH_A, MASK_A),
+    (switches::SWITCH_B, MASK_B),
+    (switches::SWITCH_X, MASK_X),
+    (switches::SWITCH_Y, MASK_Y),
+    (switches::SWITCH_L1, MASK_R1),
+    (switches::SWITCH_R1, MASK_R2),
+    (switches::SWITCH_L2, MASK_L1),
+    (switches::SWITCH_R2, MASK_L2),
+    (switches::SWITCH_SELECT, MASK_HOME),
+    (switches::SWITCH_START, MASK_SELECT),
+    (switches::SWITCH_HOME, MASK_START),
+    #[cfg(feature = "buttons-24")]
+    (switches::SWITCH_PADDLE1, MASK_PADDLE1),
+    #[cfg(feature = "buttons-24")]
+    (switches::SWITCH_PADDLE2, MASK_PADDLE2),
+    #[cfg(feature = "buttons-24")]
+    (switches::SWITCH_PADDLE3, MASK_PADDLE3),
+    #[cfg(feature = "buttons-24")]
+    (switches::SWITCH_PADDLE4, MASK_PADDLE4),
+];
+
+// Shift layer, only the switches it remaps while Shift is held
+// Every other switch keeps its BASE_LAYER mask
+// Shift itself is never reported, but it also drives the Dpad SOCD rules,
+// Smash half tilt and the Analog right stick, and Shift+Home changes mode.
+// Remapping SWITCH_HOME here changes what is reported during that combo.
+// Empty by default, so Shift+A stays a Smash tilt. To report X instead, add:
+// (switches::SWITCH_A, MASK_X),
+static SHIFT_LAYER: &[(usize, Buttons)] = &[];
+
 fn button_read(signals: &[Switch], mode: InputMode) -> KeyData {
     // Set the report content
-    let mut stickreport = KeyData {
-        buttons: MASK_NONE,
-        hat: PAD_MASK_NONE,
-        padding: 0,
-        lx: 128,
-        ly: 128,
-        rx: 128,
-        ry: 128,
-    };
+    let mut stickreport = KeyData::new();
 
     match mode {
         InputMode::Smash => process_smash(signals, &mut stickreport),
         InputMode::Analog => process_analog(signals, &mut stickreport),
         InputMode::Dpad => process_dpad(signals, &mut stickreport),
     };
+    stickreport.lx = trim_axis(stickreport.lx, AXIS_TRIM[0]);
+    stickreport.ly = trim_axis(stickreport.ly, AXIS_TRIM[1]);
+    stickreport.rx = trim_axis(stickreport.rx, AXIS_TRIM[2]);
+    stickreport.ry = trim_axis(stickreport.ry, AXIS_TRIM[3]);
 
-    // read buttons
-    // if button is pressed, set the bit
-    if signals[switches::SWITCH_A].is_high() {
-        stickreport.buttons |= MASK_A;
+    // Left+Right held but cleaned to neutral presses the dash mask
+    let (_, _, left, right) = read_directions(signals);
+    let (_, _, switch_left, switch_right) = dpad_switches();
+    if !left && !right && signals[switch_left].is_high() && signals[switch_right].is_high() {
+        stickreport.buttons |= SOCD_DASH_MASK;
     }
-    if signals[switches::SWITCH_B].is_high() {
-        stickreport.buttons |= MASK_B;
-    }
-    if signals[switches::SWITCH_X].is_high() {
-        stickreport.buttons |= MASK_X;
-    }
-    if signals[switches::SWITCH_Y].is_high() {
-        stickreport.buttons |= MASK_Y;
-    }
-    if signals[switches::SWITCH_L1].is_high() {
-        stickreport.buttons |= MASK_R1;
-    }
-    if signals[switches::SWITCH_R1].is_high() {
-        stickreport.buttons |= MASK_R2;
-    }
-    if signals[switches::SWITCH_L2].is_high() {
-        stickreport.buttons |= MASK_L1;
-    }
-    if signals[switches::SWITCH_R2].is_high() {
-        stickreport.buttons |= MASK_L2;
-    }
-    if signals[switches::SWITCH_SELECT].is_high() {
-        stickreport.buttons |= MASK_HOME;
-    }
-    if signals[switches::SWITCH_START].is_high() {
-        stickreport.buttons |= MASK_SELECT;
+
+    // read buttons through the base layer, or the shift layer's remaps
+    // if button is pressed, set the bit
+    let shift = signals[switches::SWITCH_SHIFT].is_high();
+    for (switch, mask) in BASE_LAYER.iter() {
+        if signals[*switch].is_high() {
+            let remap = SHIFT_LAYER.iter().find(|(remapped, _)| remapped == switch);
+            let mask = match remap {
+                Some((_, shift_mask)) if shift => *shift_mask,
+                _ => *mask,
+            };
+            // Hold back Home until it has been held for HOME_HOLD_FRAMES polls
+            if mask & MASK_HOME != 0 && signals[*switch].held_frames() < HOME_HOLD_FRAMES {
+                continue;
+            }
+            stickreport.buttons |= mask;
+        }
     }
-    if signals[switches::SWITCH_HOME].is_high() {
-        stickreport.buttons |= MASK_START;
+
+    // Non-standard input display summary, see KeyData::input_summary
+    if cfg!(feature = "input-display") {
+        stickreport.padding = stickreport.input_summary();
     }
     return stickreport;
 }
@@ -265,6 +418,13 @@ fn shipit(stickreport: &report::KeyData) {
 fn main() -> ! {
     // Package the keys into a struct
     let mut gamepad_signals = switches::build_gamepad();
+    switches::set_gamepad_debounce(&mut gamepad_signals, DEBOUNCE_ENABLED);
+    switches::set_gamepad_debounce_algorithm(&mut gamepad_signals, DEBOUNCE_ALGORITHM);
+    for (group, cap) in DEBOUNCE_GROUPS.iter() {
+        if let Some(cap) = cap {
+            switches::set_group_debounce(&mut gamepad_signals, *group, *cap);
+        }
+    }
     let mut indicators = switches::build_indicators();
 
     // Set the initial state of the LEDs and input mode
@@ -1,8 +1,73 @@
+use core::fmt;
 use usbd_hid_device::HidReport;
 
+// Button bitfield, widened to 24 buttons with the buttons-24 feature
+#[cfg(not(feature = "buttons-24"))]
+pub type Buttons = u16;
+#[cfg(feature = "buttons-24")]
+pub type Buttons = u32;
+
+// Bytes of button bits in the report, and how many buttons they carry
+const BUTTON_BYTES: usize = if cfg!(feature = "buttons-24") { 3 } else { 2 };
+const BUTTON_COUNT: u8 = BUTTON_BYTES as u8 * 8;
+// Stick axes in the report, the right stick is dropped with single-stick
+const STICK_AXES: usize = if cfg!(feature = "single-stick") { 2 } else { 4 };
+// Buttons, hat, hat padding and the stick axes
+pub const REPORT_LEN: usize = BUTTON_BYTES + 2 + STICK_AXES;
+
+// Button state masks
+pub const MASK_A: Buttons = 0x0004;
+pub const MASK_B: Buttons = 0x0002;
+pub const MASK_X: Buttons = 0x0008;
+pub const MASK_Y: Buttons = 0x0001;
+pub const MASK_L1: Buttons = 0x0010;
+pub const MASK_R1: Buttons = 0x0020;
+pub const MASK_L2: Buttons = 0x0040;
+pub const MASK_R2: Buttons = 0x0080;
+pub const MASK_SELECT: Buttons = 0x0100;
+pub const MASK_START: Buttons = 0x0200;
+pub const MASK_HOME: Buttons = 0x1000;
+// Dpad directions on spare bits, used when DPAD_AS_BUTTONS is set
+pub const MASK_DPAD_UP: Buttons = 0x0400;
+pub const MASK_DPAD_DOWN: Buttons = 0x0800;
+pub const MASK_DPAD_LEFT: Buttons = 0x2000;
+pub const MASK_DPAD_RIGHT: Buttons = 0x4000;
+#[cfg(feature = "buttons-24")]
+pub const MASK_PADDLE1: Buttons = 0x010000;
+#[cfg(feature = "buttons-24")]
+pub const MASK_PADDLE2: Buttons = 0x020000;
+#[cfg(feature = "buttons-24")]
+pub const MASK_PADDLE3: Buttons = 0x040000;
+#[cfg(feature = "buttons-24")]
+pub const MASK_PADDLE4: Buttons = 0x080000;
+pub const MASK_NONE: Buttons = 0x0000;
+
+// Dpad Hat switch state masks
+pub const PAD_MASK_UP: u8 = 0x00;
+pub const PAD_MASK_UPRIGHT: u8 = 0x01;
+pub const PAD_MASK_RIGHT: u8 = 0x02;
+pub const PAD_MASK_DOWNRIGHT: u8 = 0x03;
+pub const PAD_MASK_DOWN: u8 = 0x04;
+pub const PAD_MASK_DOWNLEFT: u8 = 0x05;
+pub const PAD_MASK_LEFT: u8 = 0x06;
+pub const PAD_MASK_UPLEFT: u8 = 0x07;
+pub const PAD_MASK_NONE: u8 = 0x08;
+
+// Resting value reported for each stick axis: LX, LY, RX, RY
+pub const AXIS_NEUTRAL: [u8; 4] = [128, 128, 128, 128];
+
+// Input display flags, one per input group, see KeyData::input_summary
+const DISPLAY_DIRECTIONS: u8 = 0x01;
+const DISPLAY_FACE: u8 = 0x02;
+const DISPLAY_SHOULDER: u8 = 0x04;
+const DISPLAY_MENU: u8 = 0x08;
+const DISPLAY_LEFT_STICK: u8 = 0x10;
+const DISPLAY_RIGHT_STICK: u8 = 0x20;
+const DISPLAY_OTHER: u8 = 0x40;
+
 #[derive(Debug, Copy, Clone)]
 pub struct KeyData {
-    pub buttons: u16,
+    pub buttons: Buttons,
     pub hat: u8,
     pub padding: u8,
     pub lx: u8,
@@ -11,31 +76,151 @@ pub struct KeyData {
     pub ry: u8,
 }
 
-/// Hid report for a 3-button mouse with a wheel.
+impl KeyData {
+    /// A report with no buttons pressed, the hat released and the sticks at `AXIS_NEUTRAL`.
+    pub const fn new() -> Self {
+        KeyData {
+            buttons: MASK_NONE,
+            hat: PAD_MASK_NONE,
+            padding: 0,
+            lx: AXIS_NEUTRAL[0],
+            ly: AXIS_NEUTRAL[1],
+            rx: AXIS_NEUTRAL[2],
+            ry: AXIS_NEUTRAL[3],
+        }
+    }
+
+    /// Non-standard summary of which input groups are active, one flag each.
+    /// A host-side overlay reads it from the vendor byte with input-display.
+    /// Sticks count as active when moved off `AXIS_NEUTRAL`.
+    pub const fn input_summary(&self) -> u8 {
+        let face = MASK_A | MASK_B | MASK_X | MASK_Y;
+        let shoulder = MASK_L1 | MASK_R1 | MASK_L2 | MASK_R2;
+        let menu = MASK_SELECT | MASK_START | MASK_HOME;
+        let dpad = MASK_DPAD_UP | MASK_DPAD_DOWN | MASK_DPAD_LEFT | MASK_DPAD_RIGHT;
+        let mut summary = 0;
+        if self.hat != PAD_MASK_NONE || self.buttons & dpad != 0 {
+            summary |= DISPLAY_DIRECTIONS;
+        }
+        if self.buttons & face != 0 {
+            summary |= DISPLAY_FACE;
+        }
+        if self.buttons & shoulder != 0 {
+            summary |= DISPLAY_SHOULDER;
+        }
+        if self.buttons & menu != 0 {
+            summary |= DISPLAY_MENU;
+        }
+        if self.lx != AXIS_NEUTRAL[0] || self.ly != AXIS_NEUTRAL[1] {
+            summary |= DISPLAY_LEFT_STICK;
+        }
+        if self.rx != AXIS_NEUTRAL[2] || self.ry != AXIS_NEUTRAL[3] {
+            summary |= DISPLAY_RIGHT_STICK;
+        }
+        // Paddles and any custom bits, such as SOCD_DASH_MASK
+        if self.buttons & !(face | shoulder | menu | dpad) != 0 {
+            summary |= DISPLAY_OTHER;
+        }
+        summary
+    }
+}
+
+impl Default for KeyData {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Display name for each button mask, in display order
+static BUTTON_NAMES: &[(Buttons, &str)] = &[
+    (MASK_A, "A"),
+    (MASK_B, "B"),
+    (MASK_X, "X"),
+    (MASK_Y, "Y"),
+    (MASK_L1, "L1"),
+    (MASK_R1, "R1"),
+    (MASK_L2, "L2"),
+    (MASK_R2, "R2"),
+    (MASK_SELECT, "Select"),
+    (MASK_START, "Start"),
+    (MASK_HOME, "Home"),
+    (MASK_DPAD_UP, "Up"),
+    (MASK_DPAD_DOWN, "Down"),
+    (MASK_DPAD_LEFT, "Left"),
+    (MASK_DPAD_RIGHT, "Right"),
+    #[cfg(feature = "buttons-24")]
+    (MASK_PADDLE1, "P1"),
+    #[cfg(feature = "buttons-24")]
+    (MASK_PADDLE2, "P2"),
+    #[cfg(feature = "buttons-24")]
+    (MASK_PADDLE3, "P3"),
+    #[cfg(feature = "buttons-24")]
+    (MASK_PADDLE4, "P4"),
+];
+
+// Hat directions, indexed by the hat value
+static HAT_NAMES: [&str; 8] = ["↑", "↗", "→", "↘", "↓", "↙", "←", "↖"];
+
+/// Compact summary for logs, e.g. `A B [↗] L:(128,128) R:(200,60)`.
+impl fmt::Display for KeyData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut named = MASK_NONE;
+        for (mask, name) in BUTTON_NAMES.iter() {
+            named |= *mask;
+            if self.buttons & *mask != 0 {
+                write!(f, "{} ", name)?;
+            }
+        }
+        // Bits without a name, e.g. from a custom SOCD_DASH_MASK
+        for bit in 0..BUTTON_COUNT {
+            let mask: Buttons = 1 << bit;
+            if self.buttons & !named & mask != 0 {
+                write!(f, "b{} ", bit)?;
+            }
+        }
+        match HAT_NAMES.get(self.hat as usize) {
+            Some(direction) => write!(f, "[{}]", direction)?,
+            None => write!(f, "[ ]")?,
+        }
+        write!(f, " L:({},{})", self.lx, self.ly)?;
+        if cfg!(feature = "single-stick") {
+            return Ok(());
+        }
+        write!(f, " R:({},{})", self.rx, self.ry)
+    }
+}
+
+/// Hid report for a generic gamepad.
 pub struct PadReport {
     // Bytes usage:
-    // byte 0..1: bits 0..13 = buttons, 14 and 15 are unused at this time
-    // byte 2: dpad hat switch
-    // byte 3: padding for hat switch
-    // byte 4: L stick X
-    // byte 5: L stick Y
-    // byte 6: R stick X
-    // byte 7: R stick Y
-    bytes: [u8; 8],
+    // byte 0..1: bits 0..15 = buttons, see the MASK_* consts
+    // (byte 2: bits 16..23 = buttons, only with the buttons-24 feature)
+    // then, in order:
+    // dpad hat switch
+    // padding for hat switch, the input summary with input-display
+    // L stick X, 0 is left
+    // L stick Y, 0 is up
+    // R stick X, 0 is left (not sent with single-stick)
+    // R stick Y, 0 is up (not sent with single-stick)
+    bytes: [u8; REPORT_LEN],
 }
 
 impl PadReport {
-    pub fn new(btnstate: &KeyData) -> Self {
-        let btnarray = btnstate.buttons.to_be_bytes();
+    pub const fn new(btnstate: &KeyData) -> Self {
+        let btnarray = btnstate.buttons.to_le_bytes();
         PadReport { 
             bytes: [ 
                 btnarray[0], 
                 btnarray[1], 
+                #[cfg(feature = "buttons-24")]
+                btnarray[2],
                 btnstate.hat, 
-                0x00, // padding for hat switch
+                btnstate.padding, // padding for hat switch, or the input summary
                 btnstate.lx, 
                 btnstate.ly, 
+                #[cfg(not(feature = "single-stick"))]
                 btnstate.rx, 
+                #[cfg(not(feature = "single-stick"))]
                 btnstate.ry, 
             ],
         }
@@ -52,52 +237,68 @@ impl AsRef<[u8]> for PadReport {
     }
 }
 
+// Top level HID usage, Joystick (0x04) or Game Pad (0x05)
+const PAD_USAGE: u8 = if cfg!(feature = "joystick-usage") { 0x04 } else { 0x05 };
+
+// Hat padding byte, constant or the input display data (Data,Var,Abs)
+const PADDING_INPUT: u8 = if cfg!(feature = "input-display") { 0x02 } else { 0x01 };
+
 impl HidReport for PadReport {
+    // Generic gamepad layout, matches the bytes built by PadReport::new
     const DESCRIPTOR: &'static [u8] = &[
-        0x08, 0x01,                   // USAGE_PAGE Generic Desktop
-        0x08, 0x05,                   // USAGE Joystick
-        0x08, 0x01,                   // COLLECTION Application
-            0x08, 0x00,               // Logical Min
-            0x08, 0x01,               // Logical Max
-            0x08, 0x00,               // Physical Min
-            0x08, 0x01,               // Physical Max
-            0x08, 0x01,               // REPORT_SIZE 1
-            0x08, 0x10,               // REPORT_COUNT 16
-            0x08, 0x09,               // USAGE PAGE
-            0x08, 0x01,               // USAGE Min
-            0x08, 0x10,               // USAGE Max
-            0x08, 0x02,               // INPUT
+        0x05, 0x01,                   // USAGE_PAGE Generic Desktop
+        0x09, PAD_USAGE,              // USAGE Game Pad or Joystick
+        0xA1, 0x01,                   // COLLECTION Application
+            // 16 buttons (24 with buttons-24), 1 bit each
+            0x15, 0x00,               // LOGICAL_MINIMUM 0
+            0x25, 0x01,               // LOGICAL_MAXIMUM 1
+            0x35, 0x00,               // PHYSICAL_MINIMUM 0
+            0x45, 0x01,               // PHYSICAL_MAXIMUM 1
+            0x75, 0x01,               // REPORT_SIZE 1
+            0x95, BUTTON_COUNT,       // REPORT_COUNT 16 or 24
+            0x05, 0x09,               // USAGE_PAGE Button
+            0x19, 0x01,               // USAGE_MINIMUM Button 1
+            0x29, BUTTON_COUNT,       // USAGE_MAXIMUM Button 16 or 24
+            0x81, 0x02,               // INPUT (Data,Var,Abs)
             // Hat switch, 1 nibble with a spare nibble
-            0x08, 0x01,               // USAGE Page
-            0x08, 0x07,               // LOGICAL Max
-            0x10, 0x01, 0x3B,            // PHYSICAL Max
-            0x08, 0x04,               // REPORT_SIZE
-            0x08, 0x01,               // REPORT_COUNT
-            0x08, 0x14,              // UNIT
-            0x08, 0x39,              // USAGE
-            0x08, 0x42,              // INPUT
+            0x05, 0x01,               // USAGE_PAGE Generic Desktop
+            0x25, 0x07,               // LOGICAL_MAXIMUM 7
+            0x46, 0x3B, 0x01,         // PHYSICAL_MAXIMUM 315
+            0x75, 0x04,               // REPORT_SIZE 4
+            0x95, 0x01,               // REPORT_COUNT 1
+            0x65, 0x14,               // UNIT Degrees
+            0x09, 0x39,               // USAGE Hat switch
+            0x81, 0x42,               // INPUT (Data,Var,Abs,Null)
             // this is where the spare nibble goes
-            0x08, 0x00,               // UNIT
-            0x08, 0x01,               // REPORT_COUNT
-            0x08, 0x01,               // INPUT
-            0x10, 0xFF, 0xFF,         // LOGICAL Max
-            0x10, 255,                // PHYSICAL Max
-            0x08, 0x08,               // USAGE
-            0x08, 0x31,               // USAGE
-            0x08, 0x32,               // USAGE
-            0x08, 0x35,               // USAGE
-            0x08, 0x08,               // REPORT SIZE
-            0x08, 0x04,               // REPORT COUNT
-            0x08, 0x02,               // INPUT
-            // vendor specific byte
-            0x10, 0xFF, 0x00,         // USAGE PAGE  (16-bit value, this hack is ugly)
-            0x08, 0x20,               // USAGE
-            0x08, 0x01,               // REPORT COUNT
-            0x08, 0x02,               // INPUT
-            // Output, 8 bytes
-            0x10, 0x26, 0x21,         // USAGE  (16-bit value, this hack is ugly)
-            0x08, 0x08,               // REPORT COUNT
-            0x08, 0x02,               // OUTPUT
-        0x00 // END COLLECTION
+            0x65, 0x00,               // UNIT None
+            0x95, 0x01,               // REPORT_COUNT 1
+            0x81, 0x01,               // INPUT (Cnst)
+            // padding byte for hat switch, a vendor input with input-display
+            #[cfg(feature = "input-display")] 0x06,
+            #[cfg(feature = "input-display")] 0x00,
+            #[cfg(feature = "input-display")] 0xFF, // USAGE_PAGE Vendor Defined 0xFF00
+            #[cfg(feature = "input-display")] 0x09,
+            #[cfg(feature = "input-display")] 0x01, // USAGE Vendor Usage 1
+            #[cfg(feature = "input-display")] 0x26,
+            #[cfg(feature = "input-display")] 0xFF,
+            #[cfg(feature = "input-display")] 0x00, // LOGICAL_MAXIMUM 255
+            0x75, 0x08,               // REPORT_SIZE 8
+            0x95, 0x01,               // REPORT_COUNT 1
+            0x81, PADDING_INPUT,      // INPUT (Cnst) or (Data,Var,Abs)
+            #[cfg(feature = "input-display")] 0x05,
+            #[cfg(feature = "input-display")] 0x01, // USAGE_PAGE Generic Desktop
+            // L stick X/Y, R stick X/Y unless single-stick, 1 byte each
+            0x26, 0xFF, 0x00,         // LOGICAL_MAXIMUM 255
+            0x46, 0xFF, 0x00,         // PHYSICAL_MAXIMUM 255
+            0x09, 0x30,               // USAGE X
+            0x09, 0x31,               // USAGE Y
+            #[cfg(not(feature = "single-stick"))] 0x09,
+            #[cfg(not(feature = "single-stick"))] 0x32, // USAGE Z
+            #[cfg(not(feature = "single-stick"))] 0x09,
+            #[cfg(not(feature = "single-stick"))] 0x35, // USAGE Rz
+            0x75, 0x08,               // REPORT_SIZE 8
+            0x95, STICK_AXES as u8,   // REPORT_COUNT 4, or 2 with single-stick
+            0x81, 0x02,               // INPUT (Data,Var,Abs)
+        0xC0                          // END_COLLECTION
     ];
 }
@@ -0,0 +1,21 @@
+/// SOCD tie-break for a pair of opposing directions pressed on the same frame
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SocdTieBreak {
+    Neutral,
+    UpOrLeft,
+    DownOrRight,
+}
+
+// Resolve a pair of opposing directions, up or left first, using the given tie-break
+// Returns the resolved (up_or_left, down_or_right)
+pub fn resolve_socd(up_or_left: bool, down_or_right: bool, tie_break: SocdTieBreak) -> (bool, bool) {
+    if up_or_left && down_or_right {
+        match tie_break {
+            SocdTieBreak::Neutral => (false, false),
+            SocdTieBreak::UpOrLeft => (true, false),
+            SocdTieBreak::DownOrRight => (false, true),
+        }
+    } else {
+        (up_or_left, down_or_right)
+    }
+}
@@ -1,23 +1,35 @@
 use debouncr::{debounce_8, Debouncer, Edge, Repeat8};
+use crate::debounce::Integrator;
 use arduino_hal;
 
 // Define the array offsets for each switch
-pub static SWITCH_A: usize = 0;
-pub static SWITCH_B: usize = 1;
-pub static SWITCH_X: usize = 2;
-pub static SWITCH_Y: usize = 3;
-pub static SWITCH_L1: usize = 4;
-pub static SWITCH_R1: usize = 5;
-pub static SWITCH_L2: usize = 6;
-pub static SWITCH_R2: usize = 7;
-pub static SWITCH_SELECT: usize = 8;
-pub static SWITCH_START: usize = 9;
-pub static SWITCH_HOME: usize = 10;
-pub static SWITCH_SHIFT: usize = 11;
-pub static SWITCH_UP: usize = 12;
-pub static SWITCH_DOWN: usize = 13;
-pub static SWITCH_LEFT: usize = 14;
-pub static SWITCH_RIGHT: usize = 15;
+pub const SWITCH_A: usize = 0;
+pub const SWITCH_B: usize = 1;
+pub const SWITCH_X: usize = 2;
+pub const SWITCH_Y: usize = 3;
+pub const SWITCH_L1: usize = 4;
+pub const SWITCH_R1: usize = 5;
+pub const SWITCH_L2: usize = 6;
+pub const SWITCH_R2: usize = 7;
+pub const SWITCH_SELECT: usize = 8;
+pub const SWITCH_START: usize = 9;
+pub const SWITCH_HOME: usize = 10;
+pub const SWITCH_SHIFT: usize = 11;
+pub const SWITCH_UP: usize = 12;
+pub const SWITCH_DOWN: usize = 13;
+pub const SWITCH_LEFT: usize = 14;
+pub const SWITCH_RIGHT: usize = 15;
+#[cfg(feature = "buttons-24")]
+pub const SWITCH_PADDLE1: usize = 16;
+#[cfg(feature = "buttons-24")]
+pub const SWITCH_PADDLE2: usize = 17;
+#[cfg(feature = "buttons-24")]
+pub const SWITCH_PADDLE3: usize = 18;
+#[cfg(feature = "buttons-24")]
+pub const SWITCH_PADDLE4: usize = 19;
+
+// Number of switches on the gamepad, including the paddles
+pub const SWITCH_COUNT: usize = if cfg!(feature = "buttons-24") { 20 } else { 16 };
 
 /// If the switch is a pull-up or pull-down type
 #[derive(Debug, Copy, Clone)]
@@ -26,6 +38,24 @@ pub enum SwitchType {
     PullDown,
 }
 
+/// How a switch filters contact bounce
+#[derive(Debug, Copy, Clone)]
+pub enum DebounceAlgorithm {
+    /// debouncr's pattern debouncer, flips after 8 matching reads in a row
+    Pattern,
+    /// Counts toward `cap` while pressed and back to 0 while released, flips at either end
+    Integrator { cap: u8 },
+}
+
+/// Groups of switches that can share a debounce setting
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ButtonGroup {
+    Dpad,
+    Face,
+    Shoulder,
+    Menu,
+}
+
 pub enum ButtonName {
     ButtonA,
     ButtonB,
@@ -43,6 +73,14 @@ pub enum ButtonName {
     ButtonDown,
     ButtonLeft,
     ButtonRight,
+    #[cfg(feature = "buttons-24")]
+    ButtonPaddle1,
+    #[cfg(feature = "buttons-24")]
+    ButtonPaddle2,
+    #[cfg(feature = "buttons-24")]
+    ButtonPaddle3,
+    #[cfg(feature = "buttons-24")]
+    ButtonPaddle4,
 }
 
 /// Process state information from a 2 state switch.
@@ -50,6 +88,10 @@ pub enum ButtonName {
 pub struct Switch {
     pin: arduino_hal::port::Pin<arduino_hal::port::mode::Input<arduino_hal::port::mode::PullUp>>,
     state: Debouncer<u8, Repeat8>,
+    debounce: bool,
+    algorithm: DebounceAlgorithm,
+    integrator: Integrator,
+    pressed: bool,
     falling: bool,
     rising: bool,
     switch_type: SwitchType,
@@ -89,9 +131,21 @@ impl Switch {
                 ButtonName::ButtonUp => { pins.d7.into_pull_up_input().downgrade() }, 
                 ButtonName::ButtonDown => { pins.d8.into_pull_up_input().downgrade() }, 
                 ButtonName::ButtonLeft => { pins.d6.into_pull_up_input().downgrade() }, 
-                ButtonName::ButtonRight => { pins.d9.into_pull_up_input().downgrade() }
+                ButtonName::ButtonRight => { pins.d9.into_pull_up_input().downgrade() },
+                #[cfg(feature = "buttons-24")]
+                ButtonName::ButtonPaddle1 => { pins.d11.into_pull_up_input().downgrade() },
+                #[cfg(feature = "buttons-24")]
+                ButtonName::ButtonPaddle2 => { pins.d12.into_pull_up_input().downgrade() },
+                #[cfg(feature = "buttons-24")]
+                ButtonName::ButtonPaddle3 => { pins.a4.into_pull_up_input().downgrade() },
+                #[cfg(feature = "buttons-24")]
+                ButtonName::ButtonPaddle4 => { pins.a5.into_pull_up_input().downgrade() },
             },
-            state: debounce_8(true),
+            state: debounce_8(false), // start released, is_high means pressed
+            debounce: true,
+            algorithm: DebounceAlgorithm::Pattern,
+            integrator: Integrator::new(),
+            pressed: false,
             falling: false,
             rising: false,
             switch_type,
@@ -122,12 +176,42 @@ impl Switch {
         };
     }
 
+    /// Pass the raw pin state straight through instead of debouncing it.
+    pub fn set_debounce_enabled(&mut self, debounce: bool) {
+        self.debounce = debounce;
+    }
+
+    /// Pick the debounce algorithm, the pattern debouncer is the default.
+    pub fn set_debounce_algorithm(&mut self, algorithm: DebounceAlgorithm) {
+        self.algorithm = algorithm;
+    }
+
     /// Read the state of the switch and update status. This should be called on a timer.
     pub fn update(&mut self) {
         let is_pressed = self.is_pressed();
+        let was_pressed = self.pressed;
+        self.pressed = is_pressed;
 
         // Handle event
-        if let Some(edge) = self.state.update(is_pressed) {
+        // The debouncer is always fed so it is in sync if re-enabled
+        let pattern_edge = self.state.update(is_pressed);
+        let integrator_edge = match self.algorithm {
+            DebounceAlgorithm::Integrator { cap } => self.integrator.update(is_pressed, cap),
+            DebounceAlgorithm::Pattern => None,
+        };
+        let edge = if self.debounce {
+            match self.algorithm {
+                DebounceAlgorithm::Pattern => pattern_edge,
+                DebounceAlgorithm::Integrator { .. } => integrator_edge,
+            }
+        } else if is_pressed && !was_pressed {
+            Some(Edge::Rising)
+        } else if !is_pressed && was_pressed {
+            Some(Edge::Falling)
+        } else {
+            None
+        };
+        if let Some(edge) = edge {
             match edge {
                 Edge::Falling => self.falling = true,
                 Edge::Rising => self.rising = true,
@@ -172,12 +256,26 @@ impl Switch {
 
     /// If the switch state is high
     pub fn is_high(&self) -> bool {
-        self.state.is_high()
+        if self.debounce {
+            match self.algorithm {
+                DebounceAlgorithm::Pattern => self.state.is_high(),
+                DebounceAlgorithm::Integrator { .. } => self.integrator.is_high(),
+            }
+        } else {
+            self.pressed
+        }
     }
 
     /// If the switch state is low
     pub fn is_low(&self) -> bool {
-        self.state.is_low()
+        if self.debounce {
+            match self.algorithm {
+                DebounceAlgorithm::Pattern => self.state.is_low(),
+                DebounceAlgorithm::Integrator { .. } => !self.integrator.is_high(),
+            }
+        } else {
+            !self.pressed
+        }
     }
 
     /// If the switch is pressed
@@ -206,6 +304,15 @@ impl Switch {
         false
     }
 
+    /// Polls the switch has been held since it last rose, 0 while released
+    pub fn held_frames(&self) -> u32 {
+        if self.is_high() {
+            self.held_counter
+        } else {
+            0
+        }
+    }
+
     /// If the switch pressed twice inside the provided threshold
     pub fn is_double(&self) -> bool {
         self.double_press
@@ -222,7 +329,7 @@ pub fn build_indicators() -> [arduino_hal::port::Pin<arduino_hal::port::mode::Ou
 }
 
 // Write the constructor for the gamepad's switches
-pub fn build_gamepad() -> [Switch; 16] {
+pub fn build_gamepad() -> [Switch; SWITCH_COUNT] {
     // let dp = arduino_hal::Peripherals::take().unwrap();
     // let mut pins = arduino_hal::pins!(dp);
     [
@@ -242,13 +349,54 @@ pub fn build_gamepad() -> [Switch; 16] {
         Switch::new(ButtonName::ButtonDown, SwitchType::PullUp),     // Button Down
         Switch::new(ButtonName::ButtonLeft, SwitchType::PullUp),     // Button Left
         Switch::new(ButtonName::ButtonRight, SwitchType::PullUp),    // Button Right
+        #[cfg(feature = "buttons-24")]
+        Switch::new(ButtonName::ButtonPaddle1, SwitchType::PullUp),  // Paddle 1
+        #[cfg(feature = "buttons-24")]
+        Switch::new(ButtonName::ButtonPaddle2, SwitchType::PullUp),  // Paddle 2
+        #[cfg(feature = "buttons-24")]
+        Switch::new(ButtonName::ButtonPaddle3, SwitchType::PullUp),  // Paddle 3
+        #[cfg(feature = "buttons-24")]
+        Switch::new(ButtonName::ButtonPaddle4, SwitchType::PullUp),  // Paddle 4
     ]
 }
 
 // Poll the debouncers and update the gamepad's state
-pub fn poll_debouncers(gamepad_signals: &mut [Switch; 16]) -> &[Switch; 16] {
+pub fn poll_debouncers(gamepad_signals: &mut [Switch; SWITCH_COUNT]) -> &[Switch; SWITCH_COUNT] {
     for switch in gamepad_signals.iter_mut() {
         switch.update();
     }
     return gamepad_signals;
+}
+
+// Turn debouncing on or off for every switch on the gamepad
+pub fn set_gamepad_debounce(gamepad_signals: &mut [Switch; SWITCH_COUNT], debounce: bool) {
+    for switch in gamepad_signals.iter_mut() {
+        switch.set_debounce_enabled(debounce);
+    }
+}
+
+// Pick the debounce algorithm for every switch on the gamepad
+pub fn set_gamepad_debounce_algorithm(gamepad_signals: &mut [Switch; SWITCH_COUNT], algorithm: DebounceAlgorithm) {
+    for switch in gamepad_signals.iter_mut() {
+        switch.set_debounce_algorithm(algorithm);
+    }
+}
+
+// Group of each switch offset, the paddles count as face buttons and Shift as menu
+pub fn button_group(switch: usize) -> ButtonGroup {
+    match switch {
+        SWITCH_UP | SWITCH_DOWN | SWITCH_LEFT | SWITCH_RIGHT => ButtonGroup::Dpad,
+        SWITCH_L1 | SWITCH_R1 | SWITCH_L2 | SWITCH_R2 => ButtonGroup::Shoulder,
+        SWITCH_SELECT | SWITCH_START | SWITCH_HOME | SWITCH_SHIFT => ButtonGroup::Menu,
+        _ => ButtonGroup::Face,
+    }
+}
+
+// Debounce one group of the gamepad's switches with an integrator of the given cap
+pub fn set_group_debounce(gamepad_signals: &mut [Switch; SWITCH_COUNT], group: ButtonGroup, cap: u8) {
+    for (switch, signal) in gamepad_signals.iter_mut().enumerate() {
+        if button_group(switch) == group {
+            signal.set_debounce_algorithm(DebounceAlgorithm::Integrator { cap });
+        }
+    }
 }
\ No newline at end of file
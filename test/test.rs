@@ -12,3 +12,110 @@ mod tests {
         assert_eq!(result, 4);
     }
 }
+
+#[cfg(test)]
+#[path = "../src/socd.rs"]
+mod socd;
+
+#[cfg(test)]
+mod socd_tests {
+    use super::socd::*;
+
+    #[test]
+    fn same_frame_press_uses_the_tie_break() {
+        // Both directions pressed on the very first resolve
+        assert_eq!(resolve_socd(true, true, SocdTieBreak::Neutral), (false, false));
+        assert_eq!(resolve_socd(true, true, SocdTieBreak::UpOrLeft), (true, false));
+        assert_eq!(resolve_socd(true, true, SocdTieBreak::DownOrRight), (false, true));
+    }
+
+    #[test]
+    fn single_direction_passes_through() {
+        for tie_break in [SocdTieBreak::Neutral, SocdTieBreak::UpOrLeft, SocdTieBreak::DownOrRight] {
+            assert_eq!(resolve_socd(true, false, tie_break), (true, false));
+            assert_eq!(resolve_socd(false, true, tie_break), (false, true));
+            assert_eq!(resolve_socd(false, false, tie_break), (false, false));
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "../src/debounce.rs"]
+mod debounce;
+
+#[cfg(test)]
+mod debounce_tests {
+    use super::debounce::Integrator;
+    use debouncr::{debounce_8, Edge};
+
+    // Read index and direction of each edge
+    type Edges = Vec<(usize, Edge)>;
+
+    // Feed a signal to the pattern debouncer and an integrator side by side
+    // Returns the edges from each algorithm
+    fn compare(signal: &[bool], cap: u8, pressed: bool) -> (Edges, Edges) {
+        let mut pattern = debounce_8(pressed);
+        let mut integrator = Integrator::new();
+        if pressed {
+            for _ in 0..cap {
+                integrator.update(true, cap);
+            }
+        }
+        let (mut pattern_edges, mut integrator_edges) = (Vec::new(), Vec::new());
+        for (read, &level) in signal.iter().enumerate() {
+            if let Some(edge) = pattern.update(level) {
+                pattern_edges.push((read, edge));
+            }
+            if let Some(edge) = integrator.update(level, cap) {
+                integrator_edges.push((read, edge));
+            }
+        }
+        (pattern_edges, integrator_edges)
+    }
+
+    // A held signal that drops out on every 4th read
+    fn noisy(level: bool, reads: usize) -> Vec<bool> {
+        (0..reads).map(|read| if read % 4 == 3 { !level } else { level }).collect()
+    }
+
+    #[test]
+    fn clean_press_rises_for_both() {
+        let (pattern, integrator) = compare(&[true; 16], 4, false);
+        assert_eq!(pattern, vec![(7, Edge::Rising)]);
+        assert_eq!(integrator, vec![(3, Edge::Rising)]);
+    }
+
+    #[test]
+    fn noisy_press_only_rises_for_the_integrator() {
+        let (pattern, integrator) = compare(&noisy(true, 32), 4, false);
+        assert_eq!(pattern, vec![]);
+        assert_eq!(integrator, vec![(5, Edge::Rising)]);
+    }
+
+    #[test]
+    fn noisy_release_only_falls_for_the_integrator() {
+        let (pattern, integrator) = compare(&noisy(false, 32), 4, true);
+        assert_eq!(pattern, vec![]);
+        assert_eq!(integrator, vec![(5, Edge::Falling)]);
+    }
+
+    #[test]
+    fn single_glitch_never_reads_pressed() {
+        let mut pattern = debounce_8(false);
+        let mut integrator = Integrator::new();
+        for read in 0..16 {
+            pattern.update(read == 4);
+            integrator.update(read == 4, 4);
+            assert!(!pattern.is_high());
+            assert!(!integrator.is_high());
+        }
+    }
+
+    #[test]
+    fn zero_cap_acts_as_one() {
+        let mut integrator = Integrator::new();
+        assert_eq!(integrator.update(true, 0), Some(Edge::Rising));
+        assert_eq!(integrator.update(true, 0), None);
+        assert_eq!(integrator.update(false, 0), Some(Edge::Falling));
+    }
+}